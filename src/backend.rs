@@ -0,0 +1,113 @@
+//! Terminal-backend abstraction.
+//!
+//! The rest of the program talks to a single [`Terminal`] and a single
+//! [`Key`] type; which concrete backend sits underneath is chosen at compile
+//! time. By default the Unix-only `termion` backend is used; building with
+//! `--features crossterm` swaps in the cross-platform `crossterm` backend.
+//! Since `termion` is a default feature, a Windows build also needs
+//! `--no-default-features` so the `termion` crate is not compiled at all.
+
+/// Backend-independent key event.
+///
+/// Only the keys the UI actually reacts to are modelled; everything else
+/// collapses to [`Key::Other`] so comparisons like `key == Key::Char('q')`
+/// work the same regardless of the active backend.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Key {
+    Char(char),
+    Other,
+}
+
+#[cfg(not(feature = "crossterm"))]
+mod imp {
+    use super::Key;
+    use std::io::{self, Stdout};
+
+    use termion::input::MouseTerminal;
+    use termion::raw::{IntoRawMode, RawTerminal};
+    use termion::screen::AlternateScreen;
+    use tui::backend::TermionBackend;
+    use tui::Terminal;
+
+    pub type Backend =
+        TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<Stdout>>>>;
+
+    pub fn setup_terminal() -> Result<Terminal<Backend>, failure::Error> {
+        let stdout = io::stdout().into_raw_mode()?;
+        let stdout = MouseTerminal::from(stdout);
+        let stdout = AlternateScreen::from(stdout);
+        let backend = TermionBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+        terminal.hide_cursor()?;
+        Ok(terminal)
+    }
+
+    impl From<termion::event::Key> for Key {
+        fn from(key: termion::event::Key) -> Key {
+            match key {
+                termion::event::Key::Char(c) => Key::Char(c),
+                _ => Key::Other,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "crossterm")]
+mod imp {
+    use super::Key;
+
+    use crossterm::{AlternateScreen, KeyEvent};
+    use tui::backend::CrosstermBackend;
+    use tui::Terminal;
+
+    pub type Backend = CrosstermBackend;
+
+    pub fn setup_terminal() -> Result<Terminal<Backend>, failure::Error> {
+        let screen = AlternateScreen::to_alternate(true)?;
+        let backend = CrosstermBackend::with_alternate_screen(screen)?;
+        let mut terminal = Terminal::new(backend)?;
+        terminal.hide_cursor()?;
+        Ok(terminal)
+    }
+
+    impl From<KeyEvent> for Key {
+        fn from(key: KeyEvent) -> Key {
+            match key {
+                KeyEvent::Char(c) => Key::Char(c),
+                _ => Key::Other,
+            }
+        }
+    }
+}
+
+pub use self::imp::setup_terminal;
+
+/// Read key events from the active backend, forwarding each as a [`Key`] to
+/// `handler`. Returns when the input stream ends or `handler` returns `false`.
+#[cfg(not(feature = "crossterm"))]
+pub fn read_keys<F: FnMut(Key) -> bool>(mut handler: F) {
+    use std::io;
+    use termion::input::TermRead;
+
+    let stdin = io::stdin();
+    for key in stdin.keys().flatten() {
+        if !handler(Key::from(key)) {
+            return;
+        }
+    }
+}
+
+#[cfg(feature = "crossterm")]
+pub fn read_keys<F: FnMut(Key) -> bool>(mut handler: F) {
+    use crossterm::{input, InputEvent};
+
+    let input = input();
+    let reader = input.read_sync();
+    for event in reader {
+        if let InputEvent::Keyboard(key) = event {
+            if !handler(Key::from(key)) {
+                return;
+            }
+        }
+    }
+}
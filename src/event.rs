@@ -0,0 +1,79 @@
+use std::process::Child;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use backend::{read_keys, Key};
+use Packet;
+use PacketSource;
+
+/// Everything the main loop can be woken up by.
+///
+/// Keyboard input and the periodic redraw tick arrive on their own threads,
+/// as does every [`Packet`] read off the ping source; `Done` is sent once the
+/// source reaches EOF or the child exits.
+pub enum Event {
+    Input(Key),
+    Tick,
+    Packet(Packet),
+    Done,
+}
+
+/// Owns the background threads that feed [`Event`]s into a single channel.
+pub struct Events {
+    rx: mpsc::Receiver<Event>,
+    child: Option<Child>,
+}
+
+impl Events {
+    /// Spin up the input, tick, and packet-reader threads for `source`.
+    ///
+    /// `child` is the live `ping` process, if any; it is retained so
+    /// [`terminate`](Events::terminate) can stop it, which in turn lets the
+    /// reader thread see EOF and emit [`Event::Done`].
+    pub fn new(source: PacketSource, child: Option<Child>) -> Events {
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                read_keys(|key| tx.send(Event::Input(key)).is_ok());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            thread::spawn(move || loop {
+                if tx.send(Event::Tick).is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(250));
+            });
+        }
+
+        // The reader thread owns the source and its single, long-lived
+        // `BufReader`, pushing each parsed packet onto the channel.
+        thread::spawn(move || {
+            for packet in source {
+                if tx.send(Event::Packet(packet)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(Event::Done);
+        });
+
+        Events { rx, child }
+    }
+
+    pub fn next(&self) -> Result<Event, mpsc::RecvError> {
+        self.rx.recv()
+    }
+
+    /// Kill the live `ping`, if there is one. The reader thread then reaches
+    /// EOF and finishes on its own.
+    pub fn terminate(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.kill();
+        }
+    }
+}
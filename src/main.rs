@@ -1,119 +1,311 @@
-use std::io;
-
-use termion::event::Key;
-use termion::input::MouseTerminal;
-use termion::raw::IntoRawMode;
-use termion::screen::AlternateScreen;
-use tui::backend::TermionBackend;
+extern crate failure;
+extern crate tui;
+#[cfg(feature = "termion")]
+extern crate termion;
+#[cfg(feature = "crossterm")]
+extern crate crossterm;
+
+use tui::layout::{Constraint, Direction, Layout};
 use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Marker, Widget};
-use tui::Terminal;
+use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Text, Widget};
 
-use failure::{Error, Fail};
+use backend::{setup_terminal, Key};
 use event::*;
+use parse::parse_line;
+use record::*;
 
 use std::process::Command;
 use std::io::{BufReader, BufRead};
 
+mod backend;
 mod event;
+mod parse;
+mod record;
 
 struct App {
     received: Vec<(f64, f64)>,
     dropped: Vec<(f64, f64)>,
     max_latency: f64,
     max_seqnum: f64,
-    ping_runner: PingRunner,
+    stats: Stats,
 }
 
-impl App {
-    fn new() -> Result<App, failure::Error> {
-        let runner = PingRunner::run(std::env::args().skip(1).collect())?;
+/// A `Send`able source of [`Packet`]s.
+///
+/// Live (`PingRunner`), recording (`RecordingRunner`), and file-backed
+/// (`ReplayRunner`) sources are all boxed behind this alias so the background
+/// reader in [`Events`] can drive any of them uniformly.
+pub(crate) type PacketSource = Box<dyn Iterator<Item = Packet> + Send>;
+
+/// A packet source plus the live `ping` to terminate (if any) and the axis
+/// bounds the `App` should start with.
+type BuiltSource = (PacketSource, Option<std::process::Child>, (f64, f64));
+
+/// Streaming summary of the round-trip times seen so far.
+///
+/// Everything here is updated in O(1) per packet except the percentile
+/// readout, which keeps a sorted `Vec` of received RTTs so p50/p90/p99 can
+/// be pulled out cheaply while drawing.
+struct Stats {
+    received_count: usize,
+    dropped_count: usize,
+    sum: f64,
+    sum_of_squares: f64,
+    min: f64,
+    max: f64,
+    prev_rtt: Option<f64>,
+    sum_of_abs_deltas: f64,
+    delta_count: usize,
+    sorted: Vec<f64>,
+}
+
+impl Stats {
+    fn new() -> Stats {
+        Stats {
+            received_count: 0,
+            dropped_count: 0,
+            sum: 0.0,
+            sum_of_squares: 0.0,
+            min: f64::INFINITY,
+            max: 0.0,
+            prev_rtt: None,
+            sum_of_abs_deltas: 0.0,
+            delta_count: 0,
+            sorted: vec![],
+        }
+    }
+
+    fn record_received(&mut self, rtt: f64) {
+        // A non-finite RTT (e.g. `NaN` read back from a corrupt recording)
+        // would poison the accumulators and panic the sorted insert below, so
+        // drop it rather than fold it in.
+        if !rtt.is_finite() {
+            return;
+        }
+
+        self.received_count += 1;
+        self.sum += rtt;
+        self.sum_of_squares += rtt * rtt;
+
+        if rtt < self.min {
+            self.min = rtt;
+        }
+        if rtt > self.max {
+            self.max = rtt;
+        }
+
+        if let Some(prev) = self.prev_rtt {
+            self.sum_of_abs_deltas += (rtt - prev).abs();
+            self.delta_count += 1;
+        }
+        self.prev_rtt = Some(rtt);
+
+        // keep the sample list sorted so percentiles are a simple index
+        let idx = match self
+            .sorted
+            .binary_search_by(|probe| probe.partial_cmp(&rtt).unwrap())
+        {
+            Ok(i) | Err(i) => i,
+        };
+        self.sorted.insert(idx, rtt);
+    }
+
+    fn record_dropped(&mut self) {
+        self.dropped_count += 1;
+    }
+
+    fn mean(&self) -> f64 {
+        if self.received_count == 0 {
+            0.0
+        } else {
+            self.sum / self.received_count as f64
+        }
+    }
+
+    fn stddev(&self) -> f64 {
+        if self.received_count == 0 {
+            0.0
+        } else {
+            let mean = self.mean();
+            let variance = self.sum_of_squares / self.received_count as f64 - mean * mean;
+            variance.max(0.0).sqrt()
+        }
+    }
+
+    fn jitter(&self) -> f64 {
+        if self.delta_count == 0 {
+            0.0
+        } else {
+            self.sum_of_abs_deltas / self.delta_count as f64
+        }
+    }
 
-        let received = vec![];
-        let dropped = vec![];
+    fn loss_percent(&self) -> f64 {
+        let total = self.received_count + self.dropped_count;
+        if total == 0 {
+            0.0
+        } else {
+            self.dropped_count as f64 / total as f64 * 100.0
+        }
+    }
+
+    fn percentile(&self, p: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0.0;
+        }
+        let rank = (p / 100.0 * (self.sorted.len() - 1) as f64).round() as usize;
+        self.sorted[rank]
+    }
+
+    fn min(&self) -> f64 {
+        if self.received_count == 0 {
+            0.0
+        } else {
+            self.min
+        }
+    }
+}
 
-        Ok(App {
-            received,
-            dropped,
+impl App {
+    fn new() -> App {
+        App {
+            received: vec![],
+            dropped: vec![],
             max_latency: 10.0,
             max_seqnum: 100.0,
-            ping_runner: runner,
-        })
-    }
-
-    fn update(&mut self) {
-        for packet in self.ping_runner.by_ref().take(5) {
-            match packet {
-                Packet::Dropped{
-                    sequence_num,
-                    time,
-                } => {
-                    if sequence_num as f64 >= self.max_seqnum {
-                        self.max_seqnum = sequence_num as f64 + 5.0;
-                    }
+            stats: Stats::new(),
+        }
+    }
 
-                    if time >= self.max_latency {
-                        self.max_latency = time + 5.0;
-                    }
+    /// Fold a single packet, as delivered by the background reader, into the
+    /// graph series and the rolling [`Stats`].
+    fn consume(&mut self, packet: Packet) {
+        match packet {
+            Packet::Dropped {
+                sequence_num,
+                time,
+            } => {
+                if sequence_num as f64 >= self.max_seqnum {
+                    self.max_seqnum = sequence_num as f64 + 5.0;
+                }
 
-                    for i in 0..=sequence_num - self.dropped.len(){
-                        self.dropped.push(((self.dropped.len() + i) as f64, -1.0));
-                    }
+                if time >= self.max_latency {
+                    self.max_latency = time + 5.0;
+                }
 
-                    self.dropped[sequence_num].1 = time;
-                },
-                Packet::Received {
-                    sequence_num,
-                    time,
-                } => {
-                    if sequence_num as f64 >= self.max_seqnum {
-                        self.max_seqnum = sequence_num as f64 + 5.0;
-                    }
+                for i in 0..=sequence_num - self.dropped.len() {
+                    self.dropped.push(((self.dropped.len() + i) as f64, -1.0));
+                }
 
-                    if time >= self.max_latency {
-                        self.max_latency = time + 5.0;
-                    }
+                self.dropped[sequence_num].1 = time;
+                self.stats.record_dropped();
+            }
+            Packet::Received {
+                sequence_num,
+                time,
+            } => {
+                if sequence_num as f64 >= self.max_seqnum {
+                    self.max_seqnum = sequence_num as f64 + 5.0;
+                }
 
-                    for i in 0..=sequence_num - self.received.len(){
-                        self.received.push(((self.received.len() + i) as f64, -1.0));
-                    }
+                if time >= self.max_latency {
+                    self.max_latency = time + 5.0;
+                }
 
-                    self.received[sequence_num].1 = time;
+                for i in 0..=sequence_num - self.received.len() {
+                    self.received.push(((self.received.len() + i) as f64, -1.0));
                 }
+
+                self.received[sequence_num].1 = time;
+                self.stats.record_received(time);
             }
         }
     }
+}
 
-    pub fn terminate(&mut self) {
-        self.ping_runner.terminate();
+/// Pick a packet source based on the command line.
+///
+/// `--replay FILE` reads a recording, `--record FILE` mirrors a live run to a
+/// new file, and `--append FILE` continues an existing recording. Everything
+/// else is passed straight through to `ping`. The returned `Child`, if any,
+/// is kept alive by the caller so the live `ping` can be terminated later.
+///
+/// The final tuple element is the `(max_seqnum, max_latency)` axis bounds the
+/// `App` should start with: the caller's values for a live run, or the bounds
+/// restored from a recording's header on replay.
+fn build_source(
+    mut args: Vec<String>,
+    max_seqnum: f64,
+    max_latency: f64,
+) -> Result<BuiltSource, failure::Error> {
+    if let Some(path) = take_flag(&mut args, "--replay") {
+        let runner = ReplayRunner::open(&path)?;
+        let bounds = (runner.header.max_seqnum, runner.header.max_latency);
+        return Ok((Box::new(runner), None, bounds));
     }
+
+    let record = take_flag(&mut args, "--record");
+    let append = take_flag(&mut args, "--append");
+
+    let (runner, child) = PingRunner::run(args.clone())?;
+    let header = Header::new(args, max_seqnum, max_latency);
+
+    let source: PacketSource = if let Some(path) = record {
+        Box::new(RecordingRunner::create(runner, &path, &header)?)
+    } else if let Some(path) = append {
+        Box::new(RecordingRunner::append(runner, &path, &header)?)
+    } else {
+        Box::new(runner)
+    };
+
+    Ok((source, Some(child), (max_seqnum, max_latency)))
 }
 
-enum Packet {
+#[derive(Clone, Copy)]
+pub enum Packet {
     Received{sequence_num: usize, time: f64},
     Dropped{sequence_num: usize, time: f64},
 }
 
+/// Reads and parses a live `ping`'s stdout.
+///
+/// The owning `Child` is handed back separately by [`PingRunner::run`] so the
+/// process can be killed from the main thread while this reader — and its
+/// single, long-lived `BufReader` — lives on the background reader thread.
 struct PingRunner {
-    child: std::process::Child,
+    reader: BufReader<std::process::ChildStdout>,
     done: bool,
 }
 
 impl PingRunner {
-    pub fn run(args: Vec<String>) -> Result<PingRunner, failure::Error> {
-        let child = Command::new("ping")
+    pub fn run(args: Vec<String>) -> Result<(PingRunner, std::process::Child), failure::Error> {
+        let mut child = Command::new("ping")
             .args(&args)
             .stdout(std::process::Stdio::piped())
             .spawn()?;
 
-        Ok(PingRunner {
-            child,
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| failure::err_msg("ping produced no stdout"))?;
+
+        let runner = PingRunner {
+            reader: BufReader::new(stdout),
             done: false,
-        })
+        };
+
+        Ok((runner, child))
     }
+}
 
-    pub fn terminate(&mut self) {
-        self.child.kill();
+/// Remove `flag` and its following value from `args`, returning the value.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let pos = args.iter().position(|a| a == flag)?;
+    args.remove(pos);
+    if pos < args.len() {
+        Some(args.remove(pos))
+    } else {
+        None
     }
 }
 
@@ -125,67 +317,49 @@ impl Iterator for PingRunner {
             return None;
         }
 
-        let stdout = self.child.stdout.as_mut().unwrap();
-        let mut stdout_reader = BufReader::new(stdout);
-
-        let mut line = String::new();
-        let result = stdout_reader.read_line(&mut line);
-        if result.is_err() {
-            return None;
-        }
-
-        if line.is_empty() || line.starts_with('-') {
-            self.done = true;
-            return None;
-        }
-
-        // parse the packet result
-        let mut seq = 0usize;
-        let mut time = 0.0f64;
-
-        let parts: Vec<&str> = line.split_ascii_whitespace().collect();
-
-        if parts.first().unwrap() == &"Request" {
-            seq = parts.last().unwrap().parse::<usize>().unwrap();
-
-            return Some(Packet::Dropped{
-                sequence_num: seq,
-                time,
-            });
-        }
-
-        for part in parts {
-            if part.starts_with("icmp_seq=") {
-                seq = part["icmp_seq=".len()..].parse::<usize>().unwrap();
-            } else if part.starts_with("time=") {
-                time = part["time=".len()..].parse::<f64>().unwrap();
+        // Read lines until one parses into a packet, skipping banner and
+        // summary lines, and stopping cleanly at EOF.
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => {
+                    if let Some(packet) = parse_line(&line) {
+                        return Some(packet);
+                    }
+                }
+                Err(_) => return None,
             }
         }
-
-        Some(Packet::Received{
-            sequence_num: seq,
-            time,
-        })
     }
 }
 
 fn main() -> Result<(), failure::Error> {
-    // Terminal initialization
-    let stdout = io::stdout().into_raw_mode()?;
-    let stdout = MouseTerminal::from(stdout);
-    let stdout = AlternateScreen::from(stdout);
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.hide_cursor()?;
-
+    // Terminal initialization (backend selected at compile time)
+    let mut terminal = setup_terminal()?;
 
     // App
-    let events = Events::new();
-    let mut app = App::new()?;
+    let mut app = App::new();
+    let (source, child, (max_seqnum, max_latency)) = build_source(
+        std::env::args().skip(1).collect(),
+        app.max_seqnum,
+        app.max_latency,
+    )?;
+    app.max_seqnum = max_seqnum;
+    app.max_latency = max_latency;
+    let mut events = Events::new(source, child);
 
     loop {
         terminal.draw(|mut f| {
             let size = f.size();
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(24)].as_ref())
+                .split(size);
+
             Chart::default()
                 .block(
                     Block::default()
@@ -225,19 +399,43 @@ fn main() -> Result<(), failure::Error> {
                         .style(Style::default().fg(Color::Red))
                         .data(&app.dropped),
                 ])
-                .render(&mut f, size);
+                .render(&mut f, chunks[0]);
+
+            let stats = &app.stats;
+            let text = [
+                Text::raw(format!("min     {:>8.2} ms\n", stats.min())),
+                Text::raw(format!("avg     {:>8.2} ms\n", stats.mean())),
+                Text::raw(format!("max     {:>8.2} ms\n", stats.max)),
+                Text::raw(format!("stddev  {:>8.2} ms\n", stats.stddev())),
+                Text::raw(format!("jitter  {:>8.2} ms\n", stats.jitter())),
+                Text::raw(format!("loss    {:>8.2} %\n", stats.loss_percent())),
+                Text::raw("\n"),
+                Text::raw(format!("p50     {:>8.2} ms\n", stats.percentile(50.0))),
+                Text::raw(format!("p90     {:>8.2} ms\n", stats.percentile(90.0))),
+                Text::raw(format!("p99     {:>8.2} ms\n", stats.percentile(99.0))),
+            ];
+            Paragraph::new(text.iter())
+                .block(
+                    Block::default()
+                        .title("Statistics")
+                        .title_style(Style::default().fg(Color::Cyan).modifier(Modifier::BOLD))
+                        .borders(Borders::ALL),
+                )
+                .render(&mut f, chunks[1]);
         })?;
 
         match events.next()? {
             Event::Input(input) => {
                 if input == Key::Char('q') {
-                    app.terminate();
+                    events.terminate();
                     break;
                 }
             }
-            Event::Tick => {
-                app.update();
+            Event::Tick => {}
+            Event::Packet(packet) => {
+                app.consume(packet);
             }
+            Event::Done => {}
         }
     }
 
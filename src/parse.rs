@@ -0,0 +1,139 @@
+//! Parsing of `ping` output across platforms.
+//!
+//! Different `ping` implementations format their per-reply lines slightly
+//! differently; [`parse_line`] recognizes the common dialects and returns
+//! `None` for banner and summary lines rather than panicking the way the old
+//! inline parser did.
+
+use Packet;
+
+/// Parse a single line of `ping` output into a [`Packet`].
+///
+/// Returns `None` for anything that is not a per-reply line — banners,
+/// summary statistics, blank lines, or a timeout that carries no sequence
+/// number (Windows `Request timed out.`), which cannot be placed on the
+/// graph.
+pub fn parse_line(line: &str) -> Option<Packet> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    // Dropped / timeout lines across dialects:
+    //   Linux:   "From 10.0.0.1 icmp_seq=3 Destination Host Unreachable"
+    //   macOS:   "Request timeout for icmp_seq 12"
+    //   Windows: "Request timed out."
+    let lower = line.to_ascii_lowercase();
+    if lower.contains("unreachable") || lower.starts_with("request") {
+        return find_seqnum(line).map(|sequence_num| Packet::Dropped {
+            sequence_num,
+            time: 0.0,
+        });
+    }
+
+    // A successful reply always carries a latency field:
+    //   Linux/macOS: "... icmp_seq=1 ttl=117 time=12.3 ms"
+    //   Windows:     "Reply from 8.8.8.8: bytes=32 time=12ms TTL=117"
+    //   Windows:     "Reply from 8.8.8.8: bytes=32 time<1ms TTL=117"
+    let time = parse_time(line)?;
+
+    // Windows replies omit the sequence number, so fall back to 0.
+    let sequence_num = find_seqnum(line).unwrap_or(0);
+
+    Some(Packet::Received {
+        sequence_num,
+        time,
+    })
+}
+
+/// Pull the ICMP sequence number out of a line, if present.
+///
+/// Handles both the `icmp_seq=N` form (Linux/macOS replies) and the
+/// `icmp_seq N` form (macOS timeouts).
+fn find_seqnum(line: &str) -> Option<usize> {
+    let start = line.find("icmp_seq")? + "icmp_seq".len();
+    let digits: String = line[start..]
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    digits.parse().ok()
+}
+
+/// Read the latency (in milliseconds) out of a `time=`/`time<` field.
+fn parse_time(line: &str) -> Option<f64> {
+    let start = line
+        .find("time=")
+        .map(|i| i + "time=".len())
+        .or_else(|| line.find("time<").map(|i| i + "time<".len()))?;
+
+    let number: String = line[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+
+    number.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_line;
+    use Packet;
+
+    /// A flattened packet: `(received?, seqnum, rtt)`.
+    type Parsed = (bool, usize, f64);
+
+    fn describe(packet: Option<Packet>) -> Option<Parsed> {
+        packet.map(|p| match p {
+            Packet::Received { sequence_num, time } => (true, sequence_num, time),
+            Packet::Dropped { sequence_num, time } => (false, sequence_num, time),
+        })
+    }
+
+    #[test]
+    fn parses_known_dialects() {
+        let cases: &[(&str, Option<Parsed>)] = &[
+            // Linux reply
+            (
+                "64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=12.3 ms",
+                Some((true, 1, 12.3)),
+            ),
+            // macOS reply (more decimal places, icmp_seq starting at 0)
+            (
+                "64 bytes from 8.8.8.8: icmp_seq=0 ttl=117 time=12.345 ms",
+                Some((true, 0, 12.345)),
+            ),
+            // Windows reply (no sequence number, integer ms)
+            (
+                "Reply from 8.8.8.8: bytes=32 time=12ms TTL=117",
+                Some((true, 0, 12.0)),
+            ),
+            // Windows sub-millisecond reply
+            (
+                "Reply from 8.8.8.8: bytes=32 time<1ms TTL=117",
+                Some((true, 0, 1.0)),
+            ),
+            // macOS timeout
+            ("Request timeout for icmp_seq 12", Some((false, 12, 0.0))),
+            // Linux destination unreachable
+            (
+                "From 10.0.0.1 icmp_seq=3 Destination Host Unreachable",
+                Some((false, 3, 0.0)),
+            ),
+            // Windows timeout with no sequence number
+            ("Request timed out.", None),
+            // Banner
+            ("PING 8.8.8.8 (8.8.8.8) 56(84) bytes of data.", None),
+            // Summary statistics
+            ("rtt min/avg/max/mdev = 11.1/12.2/13.3/0.5 ms", None),
+            ("--- 8.8.8.8 ping statistics ---", None),
+            // Blank line
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(describe(parse_line(input)), *expected, "input: {:?}", input);
+        }
+    }
+}
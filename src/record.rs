@@ -0,0 +1,283 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Lines, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use Packet;
+
+/// Number of seconds represented by a `Duration`, as an `f64`.
+fn duration_secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1_000_000_000.0
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The single JSON object written on the first line of a recording.
+///
+/// It mirrors the asciicast header: enough metadata to re-open the exact
+/// graph later without touching the network.
+pub struct Header {
+    pub target: String,
+    pub args: Vec<String>,
+    pub start_timestamp: u64,
+    pub max_seqnum: f64,
+    pub max_latency: f64,
+}
+
+impl Header {
+    pub fn new(args: Vec<String>, max_seqnum: f64, max_latency: f64) -> Header {
+        let target = args
+            .iter()
+            .find(|a| !a.starts_with('-'))
+            .cloned()
+            .unwrap_or_default();
+
+        let start_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Header {
+            target,
+            args,
+            start_timestamp,
+            max_seqnum,
+            max_latency,
+        }
+    }
+
+    /// Serialize the header to its single-line JSON representation.
+    fn to_line(&self) -> String {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| format!("\"{}\"", escape(a)))
+            .collect();
+
+        format!(
+            "{{\"target\":\"{}\",\"args\":[{}],\"start_timestamp\":{},\"max_seqnum\":{},\"max_latency\":{}}}",
+            escape(&self.target),
+            args.join(","),
+            self.start_timestamp,
+            self.max_seqnum,
+            self.max_latency
+        )
+    }
+
+    /// Parse a recorded header line back into a [`Header`].
+    fn from_line(line: &str) -> Option<Header> {
+        let target = read_string_field(line, "target").unwrap_or_default();
+        let args = read_string_array(line, "args");
+        let max_seqnum = read_number_field(line, "max_seqnum")?;
+        let max_latency = read_number_field(line, "max_latency")?;
+        let start_timestamp = read_number_field(line, "start_timestamp")? as u64;
+
+        Some(Header {
+            target,
+            args,
+            start_timestamp,
+            max_seqnum,
+            max_latency,
+        })
+    }
+
+    /// The fields that identify *which run* a recording belongs to.
+    ///
+    /// The `start_timestamp` is deliberately excluded so that re-opening a
+    /// recording with `--append` succeeds even though a fresh `Header` carries
+    /// a new timestamp.
+    fn identity(&self) -> (&str, &[String]) {
+        (&self.target, &self.args)
+    }
+}
+
+fn read_string_field(line: &str, key: &str) -> Option<String> {
+    let needle = format!("\"{}\":\"", key);
+    let start = line.find(&needle)? + needle.len();
+    let rest = &line[start..];
+    let end = rest.find('"')?;
+    Some(rest[..end].to_string())
+}
+
+fn read_string_array(line: &str, key: &str) -> Vec<String> {
+    let needle = format!("\"{}\":[", key);
+    let start = match line.find(&needle) {
+        Some(i) => i + needle.len(),
+        None => return vec![],
+    };
+    let rest = &line[start..];
+    let end = match rest.find(']') {
+        Some(i) => i,
+        None => return vec![],
+    };
+
+    let inner = &rest[..end];
+    if inner.trim().is_empty() {
+        return vec![];
+    }
+
+    inner
+        .split(',')
+        .map(|item| item.trim().trim_matches('"').to_string())
+        .collect()
+}
+
+fn read_number_field(line: &str, key: &str) -> Option<f64> {
+    let needle = format!("\"{}\":", key);
+    let start = line.find(&needle)? + needle.len();
+    let rest = &line[start..];
+    let end = rest.find([',', '}']).unwrap_or(rest.len());
+    rest[..end].trim().parse::<f64>().ok()
+}
+
+/// Wraps any packet source and mirrors every yielded [`Packet`] to a
+/// recording file, tagging each with the elapsed time since the run began.
+pub struct RecordingRunner<I> {
+    inner: I,
+    file: File,
+    start: Instant,
+}
+
+impl<I> RecordingRunner<I> {
+    /// Start a fresh recording, writing the header as the first line.
+    pub fn create(inner: I, path: &str, header: &Header) -> Result<RecordingRunner<I>, failure::Error> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", header.to_line())?;
+
+        Ok(RecordingRunner {
+            inner,
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    /// Re-open an existing recording and continue appending to it.
+    ///
+    /// The target and ping args must match; otherwise the two runs describe
+    /// different targets and cannot share a timeline. The recorded
+    /// `start_timestamp` is ignored for this check since a fresh `Header`
+    /// always carries a new one.
+    pub fn append(inner: I, path: &str, header: &Header) -> Result<RecordingRunner<I>, failure::Error> {
+        let existing = File::open(path)?;
+        let mut lines = BufReader::new(existing).lines();
+
+        let first = match lines.next() {
+            Some(line) => line?,
+            None => return Err(failure::err_msg("recording is empty")),
+        };
+        let recorded =
+            Header::from_line(&first).ok_or_else(|| failure::err_msg("invalid recording header"))?;
+        if recorded.identity() != header.identity() {
+            return Err(failure::err_msg("recording header does not match current run"));
+        }
+
+        // Rewind the virtual clock so appended events continue after the last.
+        let last_elapsed = lines
+            .map_while(|line| line.ok())
+            .filter_map(|line| parse_event(&line))
+            .map(|(elapsed, _, _)| elapsed)
+            .fold(0.0f64, f64::max);
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        let start = Instant::now() - Duration::from_nanos((last_elapsed * 1_000_000_000.0) as u64);
+
+        Ok(RecordingRunner { inner, file, start })
+    }
+}
+
+impl<I: Iterator<Item = Packet>> Iterator for RecordingRunner<I> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        let packet = self.inner.next()?;
+        let elapsed = duration_secs(self.start.elapsed());
+
+        let (seq, rtt) = match packet {
+            Packet::Received { sequence_num, time } => (sequence_num, format!("{}", time)),
+            Packet::Dropped { sequence_num, .. } => (sequence_num, "null".to_string()),
+        };
+
+        // Best-effort: a recording failure shouldn't tear down the live graph.
+        let _ = writeln!(self.file, "[{},{},{}]", elapsed, seq, rtt);
+
+        Some(packet)
+    }
+}
+
+/// Parse one `[elapsed, seqnum, rtt_or_null]` event line.
+fn parse_event(line: &str) -> Option<(f64, usize, Option<f64>)> {
+    let trimmed = line.trim().trim_start_matches('[').trim_end_matches(']');
+    let parts: Vec<&str> = trimmed.split(',').map(str::trim).collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let elapsed = parts[0].parse::<f64>().ok()?;
+    let seqnum = parts[1].parse::<usize>().ok()?;
+    let rtt = if parts[2] == "null" {
+        None
+    } else {
+        Some(parts[2].parse::<f64>().ok()?)
+    };
+
+    Some((elapsed, seqnum, rtt))
+}
+
+/// Replays a recording as if it were a live `ping`, honoring the original
+/// inter-event timing by sleeping between packets.
+pub struct ReplayRunner {
+    lines: Lines<BufReader<File>>,
+    start: Instant,
+    pub header: Header,
+}
+
+impl ReplayRunner {
+    pub fn open(path: &str) -> Result<ReplayRunner, failure::Error> {
+        let file = File::open(path)?;
+        let mut lines = BufReader::new(file).lines();
+
+        let first = match lines.next() {
+            Some(line) => line?,
+            None => return Err(failure::err_msg("recording is empty")),
+        };
+        let header =
+            Header::from_line(&first).ok_or_else(|| failure::err_msg("invalid recording header"))?;
+
+        Ok(ReplayRunner {
+            lines,
+            start: Instant::now(),
+            header,
+        })
+    }
+}
+
+impl Iterator for ReplayRunner {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        loop {
+            let line = self.lines.next()?.ok()?;
+            let (elapsed, sequence_num, rtt) = match parse_event(&line) {
+                Some(event) => event,
+                None => continue,
+            };
+
+            // Sleep until this event's moment in the recorded timeline.
+            let target = Duration::from_nanos((elapsed * 1_000_000_000.0) as u64);
+            let played = self.start.elapsed();
+            if target > played {
+                thread::sleep(target - played);
+            }
+
+            return Some(match rtt {
+                Some(time) => Packet::Received { sequence_num, time },
+                None => Packet::Dropped {
+                    sequence_num,
+                    time: 0.0,
+                },
+            });
+        }
+    }
+}